@@ -16,14 +16,68 @@
 
 use cumulus_primitives_core::ParaId;
 use hex_literal::hex;
+use parachain_runtime::AuraId;
 use rococo_parachain_primitives::{AccountId, Signature};
 use sc_chain_spec::{ChainSpecExtension, ChainSpecGroup};
 use sc_service::{ChainType, Properties};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use sp_core::{sr25519, Pair, Public};
 use sp_runtime::traits::{IdentifyAccount, Verify};
 use sc_telemetry::TelemetryEndpoints;
 
+/// The balance seeded to each endowed account in `testnet_genesis`.
+///
+/// Kept within `u64::MAX` (unlike the previous `10_u128.pow(28)`) because this value goes
+/// through `with_genesis_config_patch`'s `json!()`, and `serde_json::Value::from(u128)` panics
+/// with "number out of range" for values `serde_json::Number` can't represent without the
+/// `arbitrary_precision` feature, which isn't enabled anywhere in this crate.
+const ENDOWMENT: u128 = 1_000_000 * 10_u128.pow(12);
+
+/// The candidacy bond required of genesis `pallet_collator_selection` invulnerables.
+///
+/// Kept within `u64::MAX` for the same `json!()` serialization reason as [`ENDOWMENT`]: this
+/// value is fed into "candidacyBond" through `with_genesis_config_patch`, and serde_json can't
+/// represent a u128 outside the u64 range without the `arbitrary_precision` feature.
+const COLLATOR_CANDIDACY_BOND: u128 = 16_000 * 10_u128.pow(12);
+
+/// The number of collator seats the genesis `pallet_collator_selection` aims to keep filled.
+const COLLATOR_DESIRED_CANDIDATES: u32 = 2;
+
+/// `(asset_id, admin, is_sufficient, min_balance)`, matching `pallet_assets::GenesisConfig::assets`.
+///
+/// This only produces a populated `"assets"` key in the genesis patch `testnet_genesis`
+/// builds; whether `parachain_runtime` actually has `pallet_assets` wired into
+/// `construct_runtime!` to read that key can't be confirmed from this checkout (the runtime
+/// crate isn't part of it). If it isn't, `build_state` silently ignores the unrecognized key
+/// and no demo assets are created.
+type AssetDefinition = (u32, AccountId, bool, u128);
+
+/// `(asset_id, name, symbol, decimals)`, matching `pallet_assets::GenesisConfig::metadata`.
+type AssetMetadataEntry = (u32, Vec<u8>, Vec<u8>, u8);
+
+/// `(asset_id, owner, amount)`, matching `pallet_assets::GenesisConfig::accounts`.
+type AssetEndowment = (u32, AccountId, u128);
+
+/// Two demo assets, both sufficient and owned by `admin`, pre-funded to `admin` so developers
+/// can exercise asset transfers and non-native fee payment out of the box, assuming
+/// `parachain_runtime` has `pallet_assets` configured (see [`AssetDefinition`]).
+fn demo_assets(admin: AccountId) -> (Vec<AssetDefinition>, Vec<AssetMetadataEntry>, Vec<AssetEndowment>) {
+	let assets = vec![
+		(1, admin.clone(), true, 1),
+		(2, admin.clone(), true, 1),
+	];
+	let metadata = vec![
+		(1, b"zCloak USD".to_vec(), b"zUSD".to_vec(), 6),
+		(2, b"zCloak DOT".to_vec(), b"zDOT".to_vec(), 10),
+	];
+	let accounts = vec![
+		(1, admin.clone(), 1_000_000 * 10_u128.pow(6)),
+		(2, admin, 1_000 * 10_u128.pow(10)),
+	];
+	(assets, metadata, accounts)
+}
+
 
 /// The extensions for the [`ChainSpec`].
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ChainSpecGroup, ChainSpecExtension)]
@@ -42,9 +96,72 @@ impl Extensions {
 	}
 }
 
+/// Chain-spec-side building block for letting operators target a different relay chain or
+/// para id than a [`ChainSpec`]'s embedded [`Extensions`] without recompiling the node.
+///
+/// This alone doesn't give operators a way to do that: no CLI flags construct an
+/// `ExtensionsOverrides` anywhere in this checkout (`node/src/command.rs`/`cli.rs`, where
+/// that wiring would live, aren't part of it). A command layer needs to parse the override
+/// flags and call [`ExtensionsOverrides::resolve`] before this has any operator-visible effect.
+#[derive(Debug, Clone, Default)]
+pub struct ExtensionsOverrides {
+	/// Overrides [`Extensions::relay_chain`] when set.
+	pub relay_chain: Option<String>,
+	/// Overrides [`Extensions::para_id`] when set.
+	pub para_id: Option<u32>,
+}
+
+impl ExtensionsOverrides {
+	/// Resolve the effective `relay_chain`/`para_id`, preferring `self`'s overrides and
+	/// falling back to the `Extensions` embedded in `chain_spec`.
+	///
+	/// Returns an `Err` rather than panicking when neither source has a value, since
+	/// `chain_spec` may come from an arbitrary file an operator pointed the node at.
+	pub fn resolve(&self, chain_spec: &dyn sc_service::ChainSpec) -> Result<(String, u32), String> {
+		let embedded = Extensions::try_get(chain_spec);
+		let relay_chain = self
+			.relay_chain
+			.clone()
+			.or_else(|| embedded.map(|extensions| extensions.relay_chain.clone()))
+			.ok_or("chain spec carries no relay_chain extension and none was given on the CLI")?;
+		let para_id = self
+			.para_id
+			.or_else(|| embedded.map(|extensions| extensions.para_id))
+			.ok_or("chain spec carries no para_id extension and none was given on the CLI")?;
+		Ok((relay_chain, para_id))
+	}
+}
+
 
 /// Specialized `ChainSpec` for the normal parachain runtime.
-pub type ChainSpec = sc_service::GenericChainSpec<parachain_runtime::GenesisConfig, Extensions>;
+///
+/// The genesis state is no longer baked in as a concrete `GenesisConfig` struct; instead
+/// it is built from a `serde_json` patch passed to `with_genesis_config_patch` (see
+/// `testnet_genesis` below), so the node no longer needs to know the runtime's storage layout.
+pub type ChainSpec = sc_service::GenericChainSpec<(), Extensions>;
+
+/// Names [`testnet_genesis`]'s three call sites are expected to line up with once
+/// `parachain_runtime` gains a `genesis_config_presets` module exposing matching presets
+/// through `sp_genesis_builder::GenesisBuilder`.
+///
+/// That runtime-side module is out of scope for this series (the `parachain_runtime` crate
+/// isn't part of this checkout) and nothing here reads these constants yet:
+/// `get_chain_spec`, `staging_test_net` and `starks_pc1_testnet` still assemble their full
+/// genesis patch locally rather than selecting a named preset, and their chain-spec `id`s are
+/// unrelated, pre-existing identifiers (`"local_testnet"`, `"staging_testnet"`,
+/// `"zcloak_network"`) that must not be repointed at these names without a separate,
+/// explicitly-reviewed migration, since `id` feeds `--chain` matching, default base-path
+/// naming and relay-chain registration tooling.
+pub mod preset_names {
+	/// The preset intended for quick, ephemeral single-node development chains.
+	pub const PRESET_DEVELOPMENT: &str = "development";
+	/// The preset intended to replace [`super::get_chain_spec`]'s local-testnet patch.
+	pub const PRESET_LOCAL_TESTNET: &str = "local_testnet";
+	/// The preset intended to replace [`super::staging_test_net`]'s patch.
+	pub const PRESET_STAGING: &str = "staging";
+	/// The preset intended to replace [`super::starks_pc1_testnet`]'s patch.
+	pub const PRESET_ZCLOAK_PC1: &str = "zcloak_pc1";
+}
 
 /// Helper function to generate a crypto pair from seed
 pub fn get_from_seed<TPublic: Public>(seed: &str) -> <TPublic::Pair as Pair>::Public {
@@ -63,116 +180,243 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
+/// Helper function to generate the session keys (currently just an Aura key) for a collator
+/// from seed.
+pub fn get_collator_keys_from_seed(seed: &str) -> AuraId {
+	get_from_seed::<AuraId>(seed)
+}
+
 pub fn get_chain_spec(id: ParaId) -> ChainSpec {
-	ChainSpec::from_genesis(
-		"Local Testnet",
-		"local_testnet",
-		ChainType::Local,
-		move || {
-			testnet_genesis(
-				get_account_id_from_seed::<sr25519::Public>("Alice"),
-				vec![
-					get_account_id_from_seed::<sr25519::Public>("Alice"),
-					get_account_id_from_seed::<sr25519::Public>("Bob"),
-					get_account_id_from_seed::<sr25519::Public>("Charlie"),
-					get_account_id_from_seed::<sr25519::Public>("Dave"),
-					get_account_id_from_seed::<sr25519::Public>("Eve"),
-					get_account_id_from_seed::<sr25519::Public>("Ferdie"),
-					get_account_id_from_seed::<sr25519::Public>("Alice//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Charlie//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Dave//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Eve//stash"),
-					get_account_id_from_seed::<sr25519::Public>("Ferdie//stash"),
-				],
-				id,
-			)
-		},
-		vec![],
-		None,
-		None,
-		None,
+	let root_key = get_account_id_from_seed::<sr25519::Public>("Alice");
+	let (assets, asset_metadata, asset_endowments) = demo_assets(root_key.clone());
+	ChainSpec::builder(
+		parachain_runtime::WASM_BINARY.expect("WASM binary was not build, please build it!"),
 		Extensions {
 			relay_chain: "westend-dev".into(),
 			para_id: id.into(),
 		},
 	)
+	.with_name("Local Testnet")
+	.with_id("local_testnet")
+	.with_chain_type(ChainType::Local)
+	.with_genesis_config_patch(testnet_genesis(
+		root_key,
+		vec![
+			(
+				get_account_id_from_seed::<sr25519::Public>("Alice"),
+				get_collator_keys_from_seed("Alice"),
+			),
+			(
+				get_account_id_from_seed::<sr25519::Public>("Bob"),
+				get_collator_keys_from_seed("Bob"),
+			),
+		],
+		vec![
+			get_account_id_from_seed::<sr25519::Public>("Alice"),
+			get_account_id_from_seed::<sr25519::Public>("Bob"),
+			get_account_id_from_seed::<sr25519::Public>("Charlie"),
+			get_account_id_from_seed::<sr25519::Public>("Dave"),
+			get_account_id_from_seed::<sr25519::Public>("Eve"),
+			get_account_id_from_seed::<sr25519::Public>("Ferdie"),
+			get_account_id_from_seed::<sr25519::Public>("Alice//stash"),
+			get_account_id_from_seed::<sr25519::Public>("Bob//stash"),
+			get_account_id_from_seed::<sr25519::Public>("Charlie//stash"),
+			get_account_id_from_seed::<sr25519::Public>("Dave//stash"),
+			get_account_id_from_seed::<sr25519::Public>("Eve//stash"),
+			get_account_id_from_seed::<sr25519::Public>("Ferdie//stash"),
+		],
+		assets,
+		asset_metadata,
+		asset_endowments,
+		id,
+	))
+	.build()
 }
 
 pub fn staging_test_net(id: ParaId) -> ChainSpec {
-	ChainSpec::from_genesis(
-		"Staging Testnet",
-		"staging_testnet",
-		ChainType::Live,
-		move || {
-			testnet_genesis(
-				hex!["9ed7705e3c7da027ba0583a22a3212042f7e715d3c168ba14f1424e2bc111d00"].into(),
-				vec![
-					hex!["9ed7705e3c7da027ba0583a22a3212042f7e715d3c168ba14f1424e2bc111d00"].into(),
-				],
-				id,
-			)
-		},
-		Vec::new(),
-		None,
-		None,
-		None,
+	ChainSpec::builder(
+		parachain_runtime::WASM_BINARY.expect("WASM binary was not build, please build it!"),
 		Extensions {
 			relay_chain: "westend-dev".into(),
 			para_id: id.into(),
 		},
 	)
+	.with_name("Staging Testnet")
+	.with_id("staging_testnet")
+	.with_chain_type(ChainType::Live)
+	.with_genesis_config_patch(testnet_genesis(
+		hex!["9ed7705e3c7da027ba0583a22a3212042f7e715d3c168ba14f1424e2bc111d00"].into(),
+		vec![(
+			hex!["9ed7705e3c7da027ba0583a22a3212042f7e715d3c168ba14f1424e2bc111d00"].into(),
+			get_collator_keys_from_seed("Alice"),
+		)],
+		vec![
+			hex!["9ed7705e3c7da027ba0583a22a3212042f7e715d3c168ba14f1424e2bc111d00"].into(),
+		],
+		Vec::new(),
+		Vec::new(),
+		Vec::new(),
+		id,
+	))
+	.build()
 }
 
 pub fn starks_pc1_testnet(id: ParaId) -> ChainSpec {
 	let mut properties = Properties::new();
 	properties.insert("tokenSymbol".into(), "STN".into());
 	properties.insert("tokenDecimals".into(), 18.into());
-	ChainSpec::from_genesis(
-		"zCloak Network PC1",
-		"zcloak_network",
-		ChainType::Live,
-		move || {
-			testnet_genesis(
-				hex!["5ae0bef89390c69ddceef596adb034b6b0546f5a0f9d8cb042e9288bd9e45e54"].into(),
-				vec![
-					hex!["1020e6d91d63cce6f6d961b5ec76364fe5601dd132e06a0de4dad3298ad8565a"].into(),
-				],
-				id,
-			)
-		},
-		Vec::new(),
-		TelemetryEndpoints::new(vec![("wss://telemetry.polkadot.io/submit/".into(), 0)]).ok(),
-		Some("zcloak-pc1"),
-		Some(properties),
+	ChainSpec::builder(
+		parachain_runtime::WASM_BINARY.expect("WASM binary was not build, please build it!"),
 		Extensions {
 			relay_chain: "rococo".into(),
 			para_id: id.into(),
 		},
 	)
+	.with_name("zCloak Network PC1")
+	.with_id("zcloak_network")
+	.with_chain_type(ChainType::Live)
+	.with_protocol_id("zcloak-pc1")
+	.with_properties(properties)
+	.with_telemetry_endpoints(
+		TelemetryEndpoints::new(vec![("wss://telemetry.polkadot.io/submit/".into(), 0)])
+			.expect("Telemetry endpoint should be valid; qed"),
+	)
+	.with_genesis_config_patch(testnet_genesis(
+		hex!["5ae0bef89390c69ddceef596adb034b6b0546f5a0f9d8cb042e9288bd9e45e54"].into(),
+		vec![(
+			hex!["1020e6d91d63cce6f6d961b5ec76364fe5601dd132e06a0de4dad3298ad8565a"].into(),
+			get_collator_keys_from_seed("zcloak-pc1-collator-1"),
+		)],
+		vec![
+			hex!["1020e6d91d63cce6f6d961b5ec76364fe5601dd132e06a0de4dad3298ad8565a"].into(),
+		],
+		Vec::new(),
+		Vec::new(),
+		Vec::new(),
+		id,
+	))
+	.build()
 }
 
 
+/// Builds a genesis config patch overriding the balances, sudo key, parachain id,
+/// collator/session/Aura authority set and pre-registered assets of whichever preset the
+/// runtime applies it on top of.
+///
+/// `initial_authorities` are seeded as both the collator-selection invulnerables and the
+/// session keys authoring with Aura, so a freshly generated spec can produce blocks without
+/// any manual session-key injection. `assets`/`asset_metadata`/`asset_endowments` are empty
+/// for specs that don't need demo tokens.
 fn testnet_genesis(
 	root_key: AccountId,
+	initial_authorities: Vec<(AccountId, AuraId)>,
 	endowed_accounts: Vec<AccountId>,
+	assets: Vec<AssetDefinition>,
+	asset_metadata: Vec<AssetMetadataEntry>,
+	asset_endowments: Vec<AssetEndowment>,
 	id: ParaId,
-) -> parachain_runtime::GenesisConfig {
-	parachain_runtime::GenesisConfig {
-		frame_system: parachain_runtime::SystemConfig {
-			code: parachain_runtime::WASM_BINARY
-				.expect("WASM binary was not build, please build it!")
-				.to_vec(),
-			changes_trie_config: Default::default(),
+) -> serde_json::Value {
+	json!({
+		"balances": {
+			"balances": endowed_accounts
+				.iter()
+				.cloned()
+				.map(|k| (k, ENDOWMENT))
+				.collect::<Vec<_>>(),
 		},
-		pallet_balances: parachain_runtime::BalancesConfig {
-			balances: endowed_accounts
+		"sudo": { "key": Some(root_key) },
+		"parachainInfo": { "parachainId": id },
+		"session": {
+			"keys": initial_authorities
 				.iter()
 				.cloned()
-				.map(|k| (k, 10_u128.pow(28)))
-				.collect(),
+				.map(|(account, aura)| {
+					(account.clone(), account, parachain_runtime::SessionKeys { aura })
+				})
+				.collect::<Vec<_>>(),
 		},
-		pallet_sudo: parachain_runtime::SudoConfig { key: root_key },
-		parachain_info: parachain_runtime::ParachainInfoConfig { parachain_id: id },
-	}
+		"aura": { "authorities": Vec::<AuraId>::new() },
+		"collatorSelection": {
+			"invulnerables": initial_authorities
+				.iter()
+				.cloned()
+				.map(|(account, _)| account)
+				.collect::<Vec<_>>(),
+			"candidacyBond": COLLATOR_CANDIDACY_BOND,
+			"desiredCandidates": COLLATOR_DESIRED_CANDIDATES,
+		},
+		"assets": {
+			"assets": assets,
+			"metadata": asset_metadata,
+			"accounts": asset_endowments,
+		},
+	})
+}
+
+/// Extracts the parachain's validation WASM (the `:code:` entry) from a [`ChainSpec`]'s
+/// genesis storage.
+///
+/// This is the chain-spec-side building block an `export-genesis-wasm` subcommand would call;
+/// no such subcommand exists in this checkout, since it would live in `node/src/command.rs`,
+/// which isn't part of this series.
+pub fn extract_genesis_wasm(chain_spec: &dyn sc_service::ChainSpec) -> Result<Vec<u8>, String> {
+	let mut storage = chain_spec.build_storage()?;
+	storage
+		.top
+		.remove(sp_core::storage::well_known_keys::CODE)
+		.ok_or_else(|| "Could not find wasm file in genesis state!".into())
+}
+
+/// Computes the genesis header: the state root over a [`ChainSpec`]'s genesis storage and an
+/// empty extrinsics root, as the relay chain expects when registering this parachain.
+///
+/// The header is returned as a value, not as bytes; its SCALE encoding (`Encode::encode`) is
+/// the genesis state that gets registered.
+///
+/// This is the chain-spec-side building block an `export-genesis-state` subcommand would
+/// call; no such subcommand exists in this checkout, for the same reason as
+/// [`extract_genesis_wasm`].
+pub fn generate_genesis_header<Block: sp_runtime::traits::Block>(
+	chain_spec: &dyn sc_service::ChainSpec,
+	genesis_state_version: sp_runtime::StateVersion,
+) -> Result<Block::Header, String> {
+	use sp_runtime::codec::Encode;
+	use sp_runtime::traits::{Hash as HashT, Header as HeaderT, Zero};
+
+	let storage = chain_spec.build_storage()?;
+
+	// Each child trie's own root is folded into the top-level trie as a regular entry, the
+	// same way the runtime computes its state root, so genesis state seeded into a child trie
+	// (if any pallet does that) is reflected in the exported header too.
+	let child_roots = storage.children_default.values().map(|child_storage| {
+		let child_root = <<Block::Header as HeaderT>::Hashing as HashT>::trie_root(
+			child_storage.data.iter().map(|(k, v)| (&k[..], &v[..])).collect(),
+			genesis_state_version,
+		);
+		(
+			child_storage.child_info.prefixed_storage_key().into_inner(),
+			child_root.encode(),
+		)
+	});
+	let state_root = <<Block::Header as HeaderT>::Hashing as HashT>::trie_root(
+		storage
+			.top
+			.iter()
+			.map(|(k, v)| (k.clone(), v.clone()))
+			.chain(child_roots)
+			.collect(),
+		genesis_state_version,
+	);
+	let extrinsics_root = <<Block::Header as HeaderT>::Hashing as HashT>::trie_root(
+		Vec::new(),
+		genesis_state_version,
+	);
+
+	Ok(HeaderT::new(
+		Zero::zero(),
+		extrinsics_root,
+		state_root,
+		Default::default(),
+		Default::default(),
+	))
 }